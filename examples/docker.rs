@@ -1,6 +1,6 @@
 use std::env::args;
 
-use replicate::Replicate;
+use replicate::{docker::Docker, Replicate};
 
 // Run inside alpine:3, which requires this example to be built with musl (assuming it
 // has not been built on alpine)
@@ -12,11 +12,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         let copy = Replicate::new()?;
         println!("My copy's path is {}", copy.display());
 
-        let pathstr = copy.display().to_string();
-        let map = format!("{0}:{0}", pathstr);
-        let mut child = std::process::Command::new("docker")
-            .args(["run", "-t", "-v", &map, IMAGE, &pathstr, "inside"])
-            .spawn()?;
+        let docker = Docker::new(&copy);
+        let container_path = docker.container_path()?.display().to_string();
+        let args = docker.run_args(IMAGE, &[&container_path, "inside"])?;
+
+        let mut child = std::process::Command::new("docker").args(args).spawn()?;
         let ecode = child.wait()?;
         assert!(ecode.success());
     } else {
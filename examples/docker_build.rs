@@ -1,6 +1,6 @@
 use std::env::args;
 
-use replicate::Replicate;
+use replicate::{docker::Docker, Replicate};
 
 // Run inside alpine:3, which requires this example to be built with musl (assuming it
 // has not been built on alpine)
@@ -13,10 +13,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         println!("My copy's path is {}", copy.display());
 
         // Use the parent path of the copied executable as a build-context for Docker
+        let docker = Docker::new(&copy);
         let args: Vec<String> = vec![
             "build".into(),
             "--build-context".into(),
-            format!("replicate={}", copy.parent().display()),
+            docker.build_context("replicate")?,
             "-f".into(),
             "examples/Dockerfile".into(),
             ".".into(),
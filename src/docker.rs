@@ -0,0 +1,184 @@
+//! Builds Docker CLI arguments around a [`Replicate`](crate::Replicate) copy, so
+//! callers don't have to hand-assemble `-v host:guest` strings or `--build-context`
+//! specs themselves.
+
+use std::path::{Path, PathBuf};
+
+use crate::Replicate;
+
+/// Default in-container path that a copy's host path is re-rooted under.
+const DEFAULT_MOUNT_POINT: &str = "/replicate";
+
+/// Builds `docker run`/`docker build` arguments for a [`Replicate`] copy.
+///
+/// # Example
+///
+/// ```no_run
+/// use replicate::{docker::Docker, Replicate};
+/// # fn main() -> std::io::Result<()> {
+/// let copy = Replicate::new()?;
+/// let docker = Docker::new(&copy);
+///
+/// let args = docker.run_args("alpine:3", &["inside"])?;
+/// std::process::Command::new("docker").args(args).spawn()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Docker<'a> {
+    replicate: &'a Replicate,
+    mount_point: PathBuf,
+}
+
+impl<'a> Docker<'a> {
+    /// Creates a builder for `replicate`, re-rooting its path under
+    /// `/replicate` inside the container.
+    pub fn new(replicate: &'a Replicate) -> Self {
+        Self {
+            replicate,
+            mount_point: PathBuf::from(DEFAULT_MOUNT_POINT),
+        }
+    }
+
+    /// Re-roots the copy's in-container path under `mount_point` instead of the
+    /// default `/replicate`.
+    pub fn with_mount_point<P: Into<PathBuf>>(mut self, mount_point: P) -> Self {
+        self.mount_point = mount_point.into();
+        self
+    }
+
+    /// Returns the path the copy will appear at inside the container, by re-rooting
+    /// its absolute host path under the configured mount point.
+    pub fn container_path(&self) -> std::io::Result<PathBuf> {
+        join_under(&self.mount_point, self.replicate.path())
+    }
+
+    /// Returns the `"{host}:{guest}"` volume spec for `docker run -v`.
+    pub fn volume_mount(&self) -> std::io::Result<String> {
+        let guest = self.container_path()?;
+        Ok(format!(
+            "{}:{}",
+            self.replicate.path().display(),
+            guest.display()
+        ))
+    }
+
+    /// Returns the `"{name}={parent_dir}"` spec for `docker build --build-context`.
+    ///
+    /// Returns an error if `replicate` is a [`Replicate::new`] or [`Replicate::from_path`]
+    /// copy, since `parent_dir` would then be the whole shared content-addressed cache —
+    /// every binary ever replicated, not just this one — rather than a directory holding
+    /// only this copy. Use a [`Replicate::persistent`] or [`Replicate::persistent_from_path`]
+    /// copy instead, whose `parent()` is a directory the caller chose for this copy alone.
+    pub fn build_context(&self, name: &str) -> std::io::Result<String> {
+        if self.replicate.is_shared_cache_copy() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "refusing to use the shared replicate-cache directory as a build context; \
+                 use a Replicate::persistent/persistent_from_path copy instead",
+            ));
+        }
+
+        Ok(format!("{name}={}", self.replicate.parent().display()))
+    }
+
+    /// Returns the full argument vector for `docker run <image> <extra...>`, with the
+    /// copy mounted via [`Docker::volume_mount`].
+    pub fn run_args(&self, image: &str, extra: &[&str]) -> std::io::Result<Vec<String>> {
+        let mount = self.volume_mount()?;
+
+        let mut args = vec![
+            "run".to_string(),
+            "-t".to_string(),
+            "-v".to_string(),
+            mount,
+            image.to_string(),
+        ];
+        args.extend(extra.iter().map(|arg| arg.to_string()));
+
+        Ok(args)
+    }
+}
+
+/// Joins `path` under `base`, stripping its leading root component so the result
+/// stays inside `base` rather than escaping back to the filesystem root. Rejects
+/// relative `path`s, since there's no host root component to strip and re-root.
+fn join_under(base: &Path, path: &Path) -> std::io::Result<PathBuf> {
+    if !path.is_absolute() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("expected an absolute path, got {}", path.display()),
+        ));
+    }
+
+    let relative: PathBuf = path.components().skip(1).collect();
+    Ok(base.join(relative))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn container_path_reroots_under_mount_point() -> anyhow::Result<()> {
+        let copy = Replicate::new()?;
+        let docker = Docker::new(&copy).with_mount_point("/mnt");
+
+        let container_path = docker.container_path()?;
+        assert!(container_path.starts_with("/mnt"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn volume_mount_joins_host_and_guest_paths() -> anyhow::Result<()> {
+        let copy = Replicate::new()?;
+        let docker = Docker::new(&copy);
+
+        let mount = docker.volume_mount()?;
+        let host = copy.path().display().to_string();
+        let guest = docker.container_path()?.display().to_string();
+        assert_eq!(mount, format!("{host}:{guest}"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn build_context_names_the_parent_directory() -> anyhow::Result<()> {
+        let copy = Replicate::same_name()?;
+        let docker = Docker::new(&copy);
+
+        let context = docker.build_context("replicate")?;
+        assert_eq!(
+            context,
+            format!("replicate={}", copy.parent().display())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn build_context_rejects_a_shared_cache_copy() -> anyhow::Result<()> {
+        let copy = Replicate::new()?;
+        let docker = Docker::new(&copy);
+
+        assert!(
+            docker.build_context("replicate").is_err(),
+            "build_context should refuse to hand back the whole shared cache directory"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn run_args_includes_volume_mount_and_extra_args() -> anyhow::Result<()> {
+        let copy = Replicate::new()?;
+        let docker = Docker::new(&copy);
+
+        let args = docker.run_args("alpine:3", &["inside"])?;
+        assert_eq!(args[0], "run");
+        assert_eq!(args.last().map(String::as_str), Some("inside"));
+        assert!(args.contains(&docker.volume_mount()?));
+
+        Ok(())
+    }
+}
@@ -10,12 +10,52 @@
 //! - Run the copied program from within the Dockerized environment.
 //!
 //! Because this library uses [`NamedTempFile`] via [`Builder`] to generate a temporary location,
-//! the following security restrictions apply to [`Replicate`]:
+//! the following security restrictions apply to [`Replicate::same_name`], [`Replicate::new_secure`]
+//! and [`Replicate::same_name_secure`], each of which copies into a fresh temporary directory
+//! that is removed when the returned [`Replicate`] is dropped (restriction 2 does not apply
+//! to the two secure variants; see below):
 //!
 //! 1. The copy has a short lifetime and your temporary file cleaner is sane (doesn’t delete recently accessed files).
 //! 2. You trust every user on your system (i.e. you are the only user).
 //! 3. You have disabled your system’s temporary file cleaner or verified that your system doesn’t have a temporary file cleaner.
 //!
+//! [`Replicate::new`] and [`Replicate::from_path`] don't fit this model: they keep a
+//! content-addressed cache of copies under `std::env::temp_dir().join("replicate-cache")`,
+//! keyed by the SHA-256 digest of the replicated executable, so repeated invocations with
+//! an unchanged binary reuse the same copy on disk instead of paying for a fresh one every
+//! time. That copy is **not** removed on drop and lives indefinitely in the cache, so
+//! restriction 1 above doesn't apply to it; restriction 2 still does, and matters more here
+//! since the cache path is predictable — a cache hit is re-hashed against the digest that
+//! names it before being trusted, rather than served back by filename alone. The digest is
+//! also available via [`Replicate::digest`] so callers can assert that a binary mounted into
+//! a container matches what was replicated.
+//!
+//! If restriction 2 above isn't acceptable, [`Replicate::new_secure`] and
+//! [`Replicate::same_name_secure`] create the parent directory and the copy with
+//! owner-only (`0o700`) permissions, so no other local user can read or execute the
+//! replicated binary. These secure variants skip the shared cache above, since a cache
+//! other users can read defeats the point.
+//!
+//! The [`docker`] module builds the `docker run`/`docker build` arguments needed to
+//! mount or build with a copy, instead of hand-assembling `-v host:guest` strings.
+//!
+//! [`Replicate::persistent`] copies into a caller-chosen directory instead of a
+//! temporary one, and does not remove the copy when dropped, so the same replicated
+//! binary can be reused across many container starts, e.g. staged once onto a
+//! persistent Docker volume. [`Replicate::cleanup`] removes a persistent directory and
+//! everything in it once it's no longer needed.
+//!
+//! Every copy is `fsync`ed before its path is handed back, so a second process (such
+//! as `docker run`/`docker build` immediately afterwards) never observes a truncated
+//! or empty file.
+//!
+//! [`Replicate::from_path`] replicates an arbitrary executable instead of the
+//! currently running program, e.g. a sibling tool or a just-built artifact, through
+//! the same content-addressed cache as [`Replicate::new`]. To stage several such
+//! binaries into one directory you control — rather than mounting the whole shared
+//! cache — use [`Replicate::persistent_from_path`] instead, which names each copy
+//! after its own file name.
+//!
 #![deny(
     missing_docs,
     trivial_casts,
@@ -27,23 +67,40 @@
 )]
 
 #[cfg(unix)]
-use std::os::unix::fs::PermissionsExt;
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
 use std::{
     fs::Permissions,
-    io::Write,
+    io::{Read, Write},
     ops::Deref,
     path::{Path, PathBuf},
 };
 
+use sha2::{Digest, Sha256};
 #[cfg(doc)]
 use tempfile::NamedTempFile;
 use tempfile::{Builder, TempDir, TempPath};
 
+pub mod docker;
+
 enum ReplicatePath {
     TempPath(TempPath),
     PathBuf(PathBuf),
 }
 
+enum ReplicateParent {
+    TempDir(TempDir),
+    Path(PathBuf),
+}
+
+impl ReplicateParent {
+    fn path(&self) -> &Path {
+        match self {
+            ReplicateParent::TempDir(temp_dir) => temp_dir.path(),
+            ReplicateParent::Path(path) => path.as_path(),
+        }
+    }
+}
+
 /// A temporary copy of the running executable.
 ///
 /// # Example
@@ -60,49 +117,376 @@ enum ReplicatePath {
 /// ```
 pub struct Replicate {
     /// The parent folder where the copy is stored.
-    parent: TempDir,
+    parent: ReplicateParent,
     /// The full path to the copy of the executable.
     path: ReplicatePath,
+    /// The lowercase hex SHA-256 digest of the copy's contents.
+    digest: String,
 }
 
 impl Replicate {
-    /// Creates a replicate of the currently running program. The
-    /// copy is deleted when this is dropped.
+    /// Creates a replicate of the currently running program.
+    ///
+    /// The copy is served out of a content-addressed cache keyed by the SHA-256 digest
+    /// of the running executable, stored under
+    /// `std::env::temp_dir().join("replicate-cache")`. A cache entry is only reused if
+    /// re-hashing it still matches the digest that names it; otherwise a fresh copy is
+    /// written and atomically moved into place so concurrent callers never observe a
+    /// partially-written file. Because the cache is shared and persistent, it is not
+    /// removed when this `Replicate` is dropped.
     pub fn new() -> Result<Self, std::io::Error> {
-        // Create a temporary directory to hold the copy.
-        let parent = tempfile::tempdir()?;
+        let (parent, path, digest) = Self::replicate_path_via_cache(exe_path()?)?;
+
+        // Return the Replicate.
+        Ok(Self {
+            parent,
+            path,
+            digest,
+        })
+    }
+
+    /// Creates a replicate of the executable at `src` (a sibling tool, a just-built
+    /// artifact, ...), through the same content-addressed cache used by
+    /// [`Replicate::new`]. `parent()` is the shared cache directory and the copy is
+    /// named after its digest, not `src`'s file name.
+    ///
+    /// To stage several different binaries into one directory you control instead —
+    /// e.g. to build a single toolchain image out of more than one replicated tool —
+    /// use [`Replicate::persistent_from_path`], which copies into a caller-chosen `dir`
+    /// and names each copy after its own file name.
+    pub fn from_path<P: AsRef<Path>>(src: P) -> std::io::Result<Self> {
+        let (parent, path, digest) = Self::replicate_path_via_cache(src)?;
+
+        Ok(Self {
+            parent,
+            path,
+            digest,
+        })
+    }
 
-        // Create a new temporary file in the temporary directory.
-        let mut copy = Builder::new()
+    /// Returns the path to the shared cache directory used by [`Replicate::new`] and
+    /// [`Replicate::from_path`].
+    fn cache_dir() -> PathBuf {
+        std::env::temp_dir().join("replicate-cache")
+    }
+
+    /// Hashes the file at `path` without writing it anywhere.
+    fn digest_of_file<P: AsRef<Path>>(path: P) -> std::io::Result<String> {
+        let (_, digest) = Self::copy_into_writer(std::fs::File::open(path)?, &mut std::io::sink())?;
+        Ok(digest)
+    }
+
+    /// Copies the executable at `src` into the content-addressed cache, reusing an
+    /// existing entry only if its contents still match the digest that names it.
+    ///
+    /// The cache lives at a predictable, world-writable path (`cache_dir().join(digest)`),
+    /// so a cache hit is re-hashed rather than trusted by filename alone: otherwise
+    /// another local user could pre-seed that path and have it served back as a
+    /// verified copy.
+    fn replicate_path_via_cache<P: AsRef<Path>>(
+        src: P,
+    ) -> std::io::Result<(ReplicateParent, ReplicatePath, String)> {
+        let src = src.as_ref();
+        let cache_dir = Self::cache_dir();
+        std::fs::create_dir_all(&cache_dir)?;
+
+        // Read src exactly once: hash it while copying it into a scratch file, so the
+        // miss path below doesn't need to open and re-read src a second time. If it
+        // turns out to be a cache hit, the scratch file is simply dropped (and deleted)
+        // unused.
+        let mut scratch = Builder::new()
             .prefix("replicate_")
             .rand_bytes(5)
-            .tempfile_in(parent.path())?;
+            .tempfile_in(&cache_dir)?;
+        let (_, digest) = Self::copy_into_writer(std::fs::File::open(src)?, &mut scratch)?;
+        let cached_path = cache_dir.join(&digest);
+
+        let cache_hit = match Self::digest_of_file(&cached_path) {
+            Ok(existing_digest) => existing_digest == digest,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => false,
+            Err(e) => return Err(e),
+        };
+
+        if !cache_hit {
+            // Make sure the bytes are actually on disk before another process can see
+            // them at their final, digest-named path.
+            scratch.as_file().sync_all()?;
+
+            Self::make_executable(scratch.path())?;
+            // Persist (atomically rename) the scratch file into place so no other
+            // process can observe a half-written binary at the digest-named path.
+            let _ = scratch.persist(&cached_path).map_err(|e| e.error)?;
+            Self::sync_parent_dir(&cached_path)?;
+        }
+
+        Ok((
+            ReplicateParent::Path(cache_dir),
+            ReplicatePath::PathBuf(cached_path),
+            digest,
+        ))
+    }
+
+    /// Copies the currently running executable into `writer`, returning the number of
+    /// bytes copied along with the lowercase hex SHA-256 digest of its contents.
+    fn copy_self_into_writer<W: ?Sized + Write>(writer: &mut W) -> std::io::Result<(u64, String)> {
+        Self::copy_into_writer(exe()?, writer)
+    }
+
+    /// Copies `reader` into `writer`, returning the number of bytes copied along with
+    /// the lowercase hex SHA-256 digest of its contents.
+    fn copy_into_writer<R: Read, W: ?Sized + Write>(
+        mut reader: R,
+        writer: &mut W,
+    ) -> std::io::Result<(u64, String)> {
+        let mut hashing_writer = HashingWriter {
+            inner: writer,
+            hasher: Sha256::new(),
+        };
+        let bytes = std::io::copy(&mut reader, &mut hashing_writer)?;
+        let digest = hex_encode(&hashing_writer.hasher.finalize());
+        Ok((bytes, digest))
+    }
+
+    /// `fsync`s the directory entry for `path`, so a rename or file creation inside it
+    /// is durable, not just the file's own contents. On non-unix targets this is a
+    /// no-op, since there's no portable way to open a directory for syncing.
+    fn sync_parent_dir<P: AsRef<Path>>(path: P) -> std::io::Result<()> {
+        #[cfg(unix)]
+        {
+            if let Some(parent) = path.as_ref().parent() {
+                std::fs::File::open(parent)?.sync_all()?;
+            }
+            Ok(())
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = path;
+            Ok(())
+        }
+    }
+
+    fn make_executable<P: AsRef<Path>>(path: P) -> std::io::Result<()> {
+        #[cfg(unix)]
+        {
+            let permissions = Permissions::from_mode(0o755);
+            std::fs::set_permissions(path.as_ref(), permissions)
+        }
+        #[cfg(not(unix))]
+        {
+            Ok(())
+        }
+    }
+
+    /// Creates a replicate of the currently running program in a directory and file
+    /// that are readable, writable and executable only by the current user.
+    ///
+    /// Unlike [`Replicate::new`], this does not go through the shared content-addressed
+    /// cache, since a cache other local users can read would defeat the purpose. The
+    /// copy is deleted when this is dropped. On non-unix targets the directory and copy
+    /// get the platform's default permissions.
+    pub fn new_secure() -> std::io::Result<Self> {
+        // Create a temporary directory readable only by the current user.
+        let parent = Self::secure_tempdir()?;
+
+        // Create the copy with owner-only permissions from the moment it exists.
+        let mut copy = Self::secure_tempfile_builder().tempfile_in(parent.path())?;
 
         // Copy the contents of this program into the copy.
-        let _ = Self::copy_self_into_writer(&mut copy)?;
+        let (_, digest) = Self::copy_self_into_writer(&mut copy)?;
+
+        // Make sure the bytes are actually on disk before handing the path back.
+        copy.as_file().sync_all()?;
+        Self::sync_parent_dir(copy.path())?;
 
         // Convert the copy into a TempPath so we can pass around the path info.
         let path = copy.into_temp_path();
 
-        // Try and make the copy executable.
-        Self::make_executable(&path)?;
+        // Try and make the copy executable, still owner-only.
+        Self::make_executable_secure(&path)?;
 
         // Return the Replicate.
         Ok(Self {
-            parent,
+            parent: ReplicateParent::TempDir(parent),
             path: ReplicatePath::TempPath(path),
+            digest,
+        })
+    }
+
+    /// Creates a replicate of the currently running program with the same name, in a
+    /// directory and file that are readable, writable and executable only by the
+    /// current user. The parent directory is cleaned up when this is dropped.
+    pub fn same_name_secure() -> std::io::Result<Self> {
+        let current_exe_path = std::env::current_exe()?;
+
+        let filename = current_exe_path
+            .file_name()
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "No file name"))?;
+
+        // Create a temporary directory readable only by the current user.
+        let parent = Self::secure_tempdir()?;
+
+        // Create a new temporary file in the temporary directory with the same name.
+        let copy_path = parent.path().join(filename);
+
+        let digest = {
+            // Open the file for writing, owner-only, from the moment it's created.
+            let mut copy = Self::create_secure_file(&copy_path)?;
+
+            // Copy the contents of this program into the copy.
+            let (_, digest) = Self::copy_self_into_writer(&mut copy)?;
+
+            // Make sure the bytes are actually on disk before handing the path back.
+            copy.sync_all()?;
+            Self::sync_parent_dir(&copy_path)?;
+
+            digest
+        };
+
+        // Try and make the copy executable, still owner-only.
+        Self::make_executable_secure(&copy_path)?;
+
+        // Return the Replicate.
+        Ok(Self {
+            parent: ReplicateParent::TempDir(parent),
+            path: ReplicatePath::PathBuf(copy_path),
+            digest,
         })
     }
 
-    fn copy_self_into_writer<W: ?Sized + Write>(writer: &mut W) -> std::io::Result<u64> {
-        let mut self_exe = exe()?;
-        std::io::copy(&mut self_exe, writer)
+    /// Creates a replicate of the currently running program in `dir`, a directory
+    /// chosen by the caller rather than a temporary one. Unlike [`Replicate::same_name`]
+    /// and the secure variants, the copy is **not** removed when this is dropped, so
+    /// it can be reused across many container starts, e.g. staged once onto a
+    /// persistent Docker volume. Call [`Replicate::cleanup`] to remove it once it's no
+    /// longer needed.
+    pub fn persistent<P: AsRef<Path>>(dir: P) -> std::io::Result<Self> {
+        // Only use current_exe() to name the copy; read its *contents* from exe_path()
+        // (e.g. /proc/self/exe on Linux), like the other constructors that copy the
+        // running program, so this still
+        // works after the on-disk binary has been replaced or deleted.
+        let current_exe_path = std::env::current_exe()?;
+
+        let filename = current_exe_path
+            .file_name()
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "No file name"))?;
+
+        Self::replicate_named_into_dir(dir, filename, exe()?)
+    }
+
+    /// Creates a replicate of the executable at `src`, named after `src`'s own file
+    /// name, in `dir` — a directory chosen by the caller rather than a temporary one.
+    /// Because the copy is named after `src` instead of its digest, several different
+    /// binaries can be staged into the same `dir`, e.g. to build a toolchain image out
+    /// of more than one replicated tool. As with [`Replicate::persistent`], the copy is
+    /// **not** removed when this is dropped; call [`Replicate::cleanup`] to remove
+    /// `dir` once it's no longer needed.
+    pub fn persistent_from_path<P: AsRef<Path>, Q: AsRef<Path>>(
+        dir: P,
+        src: Q,
+    ) -> std::io::Result<Self> {
+        let src = src.as_ref();
+        let filename = src
+            .file_name()
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "No file name"))?;
+
+        Self::replicate_named_into_dir(dir, filename, std::fs::File::open(src)?)
     }
 
-    fn make_executable<P: AsRef<Path>>(path: P) -> std::io::Result<()> {
+    /// Copies `reader` into `dir`, a directory chosen by the caller, naming the copy
+    /// `filename`. Shared by [`Replicate::persistent`] and
+    /// [`Replicate::persistent_from_path`], which differ only in where they read their
+    /// content and name from.
+    fn replicate_named_into_dir<P: AsRef<Path>, R: Read>(
+        dir: P,
+        filename: &std::ffi::OsStr,
+        reader: R,
+    ) -> std::io::Result<Self> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+
+        let copy_path = dir.join(filename);
+
+        let digest = {
+            let mut copy = std::fs::File::create(&copy_path)?;
+            let (_, digest) = Self::copy_into_writer(reader, &mut copy)?;
+
+            // Make sure the bytes are actually on disk before handing the path back.
+            copy.sync_all()?;
+            Self::sync_parent_dir(&copy_path)?;
+
+            digest
+        };
+
+        Self::make_executable(&copy_path)?;
+
+        Ok(Self {
+            parent: ReplicateParent::Path(dir.to_path_buf()),
+            path: ReplicatePath::PathBuf(copy_path),
+            digest,
+        })
+    }
+
+    /// Removes `dir` and everything in it, pruning stale copies left behind by
+    /// [`Replicate::persistent`] or [`Replicate::persistent_from_path`]. Does nothing
+    /// if `dir` doesn't exist.
+    pub fn cleanup<P: AsRef<Path>>(dir: P) -> std::io::Result<()> {
+        match std::fs::remove_dir_all(dir.as_ref()) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Creates a temporary directory that only the current user can read, write or
+    /// enter. On non-unix targets this is a regular temporary directory.
+    fn secure_tempdir() -> std::io::Result<TempDir> {
         #[cfg(unix)]
         {
-            let permissions = Permissions::from_mode(0o755);
+            Builder::new()
+                .permissions(Permissions::from_mode(0o700))
+                .tempdir()
+        }
+        #[cfg(not(unix))]
+        {
+            tempfile::tempdir()
+        }
+    }
+
+    /// A [`Builder`] that creates temp files owner-only (`0o600`) from the moment
+    /// they're created, rather than chmod-ing afterwards. `0o600` is widened to
+    /// `0o700` by [`Replicate::make_executable_secure`] once the copy is complete.
+    fn secure_tempfile_builder() -> Builder<'static, 'static> {
+        let mut builder = Builder::new();
+        let _ = builder.prefix("replicate_").rand_bytes(5);
+        #[cfg(unix)]
+        {
+            let _ = builder.permissions(Permissions::from_mode(0o600));
+        }
+        builder
+    }
+
+    /// Opens `path` for writing, owner-only (`0o600`) from the moment it's created, on
+    /// unix. On non-unix targets this is a regular file creation.
+    fn create_secure_file<P: AsRef<Path>>(path: P) -> std::io::Result<std::fs::File> {
+        #[cfg(unix)]
+        {
+            std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .mode(0o600)
+                .open(path)
+        }
+        #[cfg(not(unix))]
+        {
+            std::fs::File::create(path)
+        }
+    }
+
+    /// Makes the copy at `path` executable by the owner only (`0o700`).
+    fn make_executable_secure<P: AsRef<Path>>(path: P) -> std::io::Result<()> {
+        #[cfg(unix)]
+        {
+            let permissions = Permissions::from_mode(0o700);
             std::fs::set_permissions(path.as_ref(), permissions)
         }
         #[cfg(not(unix))]
@@ -126,21 +510,28 @@ impl Replicate {
         // Create a new temporary file in the temporary directory with the same name.
         let copy_path = parent.path().join(filename);
 
-        {
+        let digest = {
             // Open the file for writing.
             let mut copy = std::fs::File::create(&copy_path)?;
 
             // Copy the contents of this program into the copy.
-            let _ = Self::copy_self_into_writer(&mut copy)?;
-        }
+            let (_, digest) = Self::copy_self_into_writer(&mut copy)?;
+
+            // Make sure the bytes are actually on disk before handing the path back.
+            copy.sync_all()?;
+            Self::sync_parent_dir(&copy_path)?;
+
+            digest
+        };
 
         // Try and make the copy executable.
         Self::make_executable(&copy_path)?;
 
         // Return the Replicate.
         Ok(Self {
-            parent,
+            parent: ReplicateParent::TempDir(parent),
             path: ReplicatePath::PathBuf(copy_path),
+            digest,
         })
     }
 
@@ -156,6 +547,45 @@ impl Replicate {
             ReplicatePath::PathBuf(path_buf) => path_buf.as_ref(),
         }
     }
+
+    /// Returns the lowercase hex SHA-256 digest of the copy's contents.
+    pub fn digest(&self) -> &str {
+        &self.digest
+    }
+
+    /// Returns `true` if `parent()` is the shared cache directory used by
+    /// [`Replicate::new`] and [`Replicate::from_path`], rather than a directory holding
+    /// only this copy. Used by [`crate::docker::Docker::build_context`] to refuse to
+    /// use the whole cache as a build context.
+    pub(crate) fn is_shared_cache_copy(&self) -> bool {
+        self.parent() == Self::cache_dir()
+    }
+}
+
+/// Wraps a [`Write`] implementation, hashing every byte as it is written through.
+struct HashingWriter<'a, W: ?Sized + Write> {
+    inner: &'a mut W,
+    hasher: Sha256,
+}
+
+impl<W: ?Sized + Write> Write for HashingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    use std::fmt::Write as _;
+    bytes.iter().fold(String::with_capacity(bytes.len() * 2), |mut s, b| {
+        let _ = write!(s, "{b:02x}");
+        s
+    })
 }
 
 impl Deref for Replicate {
@@ -222,13 +652,48 @@ mod tests {
         let copy = Replicate::new()?;
         println!("Created new copy: {}", copy.display());
 
+        // The copy is named after its digest in the content-addressed cache.
         let name = copy
             .file_name()
             .and_then(OsStr::to_str)
             .expect("Failed to copy program");
+        assert_eq!(name, copy.digest());
+
+        // The digest is a lowercase hex SHA-256 digest.
+        assert_eq!(copy.digest().len(), 64);
+        assert!(copy.digest().chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c)));
+        Ok(())
+    }
+
+    #[test]
+    fn repeated_replicate_reuses_cache_entry() -> anyhow::Result<()> {
+        let first = Replicate::new()?;
+        let second = Replicate::new()?;
+
+        // Same running executable, so both copies should share the cache entry.
+        assert_eq!(first.digest(), second.digest());
+        assert_eq!(first.path(), second.path());
+
+        Ok(())
+    }
+
+    #[test]
+    fn tampered_cache_entry_is_rewritten() -> anyhow::Result<()> {
+        let copy = Replicate::new()?;
+
+        // Simulate another local user (or a stale run) having poisoned the
+        // digest-named cache path with unrelated content.
+        std::fs::write(copy.path(), b"not the real binary")?;
+        assert_ne!(std::fs::read(copy.path())?, std::fs::read(exe_path()?)?);
+
+        let refreshed = Replicate::new()?;
+        assert_eq!(refreshed.digest(), copy.digest());
+        assert_eq!(
+            std::fs::read(refreshed.path())?,
+            std::fs::read(exe_path()?)?,
+            "tampered cache entry was served back instead of being rewritten"
+        );
 
-        // Verify the name starts with "replicate"
-        assert!(name.starts_with("replicate"));
         Ok(())
     }
 
@@ -249,8 +714,9 @@ mod tests {
 
     #[test]
     fn test_that_files_are_cleared_up() -> anyhow::Result<()> {
+        // `same_name` owns a dedicated temporary directory, unlike `new`'s shared cache.
         let path_str = {
-            let copy = Replicate::new()?;
+            let copy = Replicate::same_name()?;
             println!("My copy's path is {}", copy.display());
             copy.parent().to_path_buf()
         }; // The copy should be cleaned up here
@@ -260,4 +726,103 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_that_cached_copies_are_not_cleared_up() -> anyhow::Result<()> {
+        let cached_path = {
+            let copy = Replicate::new()?;
+            copy.path().to_path_buf()
+        }; // Dropping the Replicate must not remove the shared cache entry.
+
+        assert!(cached_path.exists(), "Cached copy was removed on drop");
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn new_secure_is_owner_only() -> anyhow::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let copy = Replicate::new_secure()?;
+
+        let dir_mode = std::fs::metadata(copy.parent())?.permissions().mode() & 0o777;
+        assert_eq!(dir_mode, 0o700);
+
+        let file_mode = std::fs::metadata(copy.path())?.permissions().mode() & 0o777;
+        assert_eq!(file_mode, 0o700);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn same_name_secure_is_owner_only() -> anyhow::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let copy = Replicate::same_name_secure()?;
+
+        let dir_mode = std::fs::metadata(copy.parent())?.permissions().mode() & 0o777;
+        assert_eq!(dir_mode, 0o700);
+
+        let file_mode = std::fs::metadata(copy.path())?.permissions().mode() & 0o777;
+        assert_eq!(file_mode, 0o700);
+
+        Ok(())
+    }
+
+    #[test]
+    fn persistent_replica_survives_drop() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?.keep();
+        let copy_path = {
+            let copy = Replicate::persistent(&dir)?;
+            copy.path().to_path_buf()
+        }; // Dropping the Replicate must not remove the persistent copy.
+
+        assert!(copy_path.exists(), "Persistent copy was removed on drop");
+
+        Replicate::cleanup(&dir)?;
+        assert!(!dir.exists(), "cleanup() did not remove the directory");
+
+        Ok(())
+    }
+
+    #[test]
+    fn persistent_from_path_stages_several_binaries_in_one_dir() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?.keep();
+        let current_exe = std::env::current_exe()?;
+
+        // Stage the same binary under two different source names into one directory,
+        // as a toolchain image build would stage several distinct tools.
+        let a = Replicate::persistent_from_path(&dir, &current_exe)?;
+        // Keep the second source outside `dir`, so copying it doesn't truncate it.
+        let src_dir = tempfile::tempdir()?;
+        let second_src = src_dir.path().join("sibling-tool");
+        let _ = std::fs::copy(&current_exe, &second_src)?;
+        let b = Replicate::persistent_from_path(&dir, &second_src)?;
+
+        assert_eq!(a.parent(), dir);
+        assert_eq!(b.parent(), dir);
+        assert_ne!(a.path(), b.path(), "each source should get its own named copy");
+        assert!(a.path().exists());
+        assert!(b.path().exists());
+
+        Replicate::cleanup(&dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_path_replicates_an_arbitrary_executable() -> anyhow::Result<()> {
+        let src = std::env::current_exe()?;
+        let copy = Replicate::from_path(&src)?;
+
+        // The copy should have the same digest as replicating the running program,
+        // since in these tests the current executable *is* the test binary.
+        let self_copy = Replicate::new()?;
+        assert_eq!(copy.digest(), self_copy.digest());
+        assert_eq!(copy.path(), self_copy.path());
+
+        Ok(())
+    }
 }